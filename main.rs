@@ -1,136 +1,890 @@
-use std::fs;
-use std::io::{self, Read, Write};
-use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
-
-/// Append a log entry to logfile.txt in the current directory.
-fn append_log(entry: &str) {
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-    let line = format!("{} - {}\n", ts, entry);
-    if let Err(e) = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("logfile.txt")
-        .and_then(|mut f| f.write_all(line.as_bytes()))
-    {
-        eprintln!("Warning: failed to write logfile: {}", e);
-    }
-}
-
-/// Rejects unsafe filenames
-fn is_safe_path_component(name: &str) -> bool {
-    if name.contains("..") { return false; }
-    if name.starts_with('/') { return false; }
-    if name.len() > 2 {
-        let bytes = name.as_bytes();
-        if (bytes[0] as char).is_ascii_alphabetic() && bytes[1] == b':' &&
-            (bytes[2] == b'\\' || bytes[2] == b'/') {
-            return false;
-        }
-    }
-    true
-}
-
-/// Build a PathBuf inside `base` from `user_name`.
-fn resolved_path_in_base(base: &Path, user_name: &str) -> io::Result<PathBuf> {
-    if !is_safe_path_component(user_name) {
-        return Err(io::Error::new(io::ErrorKind::InvalidInput, "unsafe path"));
-    }
-    Ok(base.join(user_name))
-}
-
-/// Copy file
-fn copy_file(src: &Path, dst: &Path) -> io::Result<u64> {
-    fs::copy(src, dst)
-}
-
-/// Backup a file
-fn backup_file(base: &Path, filename: &str) -> io::Result<PathBuf> {
-    let src = resolved_path_in_base(base, filename)?;
-    if !src.exists() {
-        return Err(io::Error::new(io::ErrorKind::NotFound, "source not found"));
-    }
-    let bak_name = format!("{}.bak", filename);
-    let dst = resolved_path_in_base(base, &bak_name)?;
-    copy_file(&src, &dst)?;
-    append_log(&format!("backup {} -> {}", filename, bak_name));
-    Ok(dst)
-}
-
-/// Restore a file
-fn restore_file(base: &Path, filename: &str) -> io::Result<PathBuf> {
-    let bak_name = format!("{}.bak", filename);
-    let bak = resolved_path_in_base(base, &bak_name)?;
-    if !bak.exists() {
-        return Err(io::Error::new(io::ErrorKind::NotFound, "backup not found"));
-    }
-    let dst = resolved_path_in_base(base, filename)?;
-    copy_file(&bak, &dst)?;
-    append_log(&format!("restore {} <- {}", filename, bak_name));
-    Ok(dst)
-}
-
-/// Delete a file
-fn delete_file(base: &Path, filename: &str) -> io::Result<()> {
-    let p = resolved_path_in_base(base, filename)?;
-    if !p.exists() {
-        return Err(io::Error::new(io::ErrorKind::NotFound, "file not found"));
-    }
-    fs::remove_file(&p)?;
-    append_log(&format!("delete {}", filename));
-    Ok(())
-}
-
-/// Prompt user input
-fn prompt(msg: &str) -> io::Result<String> {
-    print!("{}", msg);
-    io::stdout().flush()?;
-    let mut s = String::new();
-    io::stdin().read_line(&mut s)?;
-    Ok(s.trim().to_string())
-}
-
-/// Pause for Enter key (keeps console open)
-fn wait_for_enter() {
-    print!("Press Enter to exit...");
-    let _ = io::stdout().flush();
-    let mut dummy = String::new();
-    let _ = io::stdin().read_line(&mut dummy);
-}
-
-fn main() -> io::Result<()> {
-    let base = std::env::current_dir()?;
-
-    println!("=== SafeBackup (Rust) ===");
-
-    let filename = prompt("Please enter your file name: ")?;
-    if !is_safe_path_component(&filename) {
-        eprintln!("Error: unsafe filename detected.");
-        append_log(&format!("rejected unsafe filename input: {}", filename));
-        wait_for_enter();
-        std::process::exit(1);
-    }
-
-    let command = prompt("Please enter your command (backup, restore, delete): ")?;
-    match command.as_str() {
-        "backup" => match backup_file(&base, &filename) {
-            Ok(dst) => println!("Your backup created: {}", dst.file_name().unwrap().to_string_lossy()),
-            Err(e) => { eprintln!("Failed to create backup: {}", e); wait_for_enter(); std::process::exit(1); }
-        },
-        "restore" => match restore_file(&base, &filename) {
-            Ok(_) => println!("File restored: {}", filename),
-            Err(e) => { eprintln!("Failed to restore: {}", e); wait_for_enter(); std::process::exit(1); }
-        },
-        "delete" => match delete_file(&base, &filename) {
-            Ok(_) => println!("File deleted: {}", filename),
-            Err(e) => { eprintln!("Failed to delete: {}", e); wait_for_enter(); std::process::exit(1); }
-        },
-        _ => { eprintln!("Invalid command."); wait_for_enter(); std::process::exit(1); }
-    }
-
-    wait_for_enter(); // keep console open after successful operation
-    Ok(())
-}
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A stable, programmatically-dispatchable category for an [`Error`]. Each kind
+/// maps to a fixed process exit code via [`Error::exit_code`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+    UnsafePath,
+    NotFound,
+    DestinationExists,
+    Io,
+}
+
+/// The crate error type. Carries enough structured context for callers to react
+/// programmatically instead of matching on free-text message strings.
+#[derive(Debug)]
+enum Error {
+    /// A user-supplied name failed the [`is_safe_path_component`] guard.
+    UnsafePath(String),
+    /// The requested path does not exist.
+    NotFound(PathBuf),
+    /// The target of a move/copy already exists and would be clobbered.
+    DestinationExists(PathBuf),
+    /// An underlying I/O failure, exposed as the error's `source`.
+    Io(io::Error),
+}
+
+impl Error {
+    /// The error's category, for programmatic handling.
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::UnsafePath(_) => ErrorKind::UnsafePath,
+            Error::NotFound(_) => ErrorKind::NotFound,
+            Error::DestinationExists(_) => ErrorKind::DestinationExists,
+            Error::Io(_) => ErrorKind::Io,
+        }
+    }
+
+    /// The stable process exit code for this error, so `main` can surface a
+    /// meaningful status instead of always exiting `1`.
+    fn exit_code(&self) -> i32 {
+        match self.kind() {
+            ErrorKind::UnsafePath => 2,
+            ErrorKind::NotFound => 3,
+            ErrorKind::DestinationExists => 4,
+            ErrorKind::Io => 5,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnsafePath(name) => write!(f, "unsafe path: {}", name),
+            Error::NotFound(path) => write!(f, "not found: {}", path.display()),
+            Error::DestinationExists(path) => {
+                write!(f, "destination already exists: {}", path.display())
+            }
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Format a Unix timestamp (seconds) as an ISO-8601 UTC string, e.g.
+/// `2024-05-17T09:41:02`. Used for `.trashinfo` deletion records.
+fn iso8601_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (hour, min, sec) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+
+    // Civil-from-days algorithm (Howard Hinnant), epoch = 1970-01-01.
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = if month <= 2 { y + 1 } else { y };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year, month, day, hour, min, sec
+    )
+}
+
+/// Append a log entry to logfile.txt in the current directory.
+fn append_log(entry: &str) {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let line = format!("{} - {}\n", ts, entry);
+    if let Err(e) = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("logfile.txt")
+        .and_then(|mut f| f.write_all(line.as_bytes()))
+    {
+        eprintln!("Warning: failed to write logfile: {}", e);
+    }
+}
+
+/// Rejects unsafe filenames
+fn is_safe_path_component(name: &str) -> bool {
+    if name.contains("..") { return false; }
+    if name.starts_with('/') { return false; }
+    if name.len() > 2 {
+        let bytes = name.as_bytes();
+        if (bytes[0] as char).is_ascii_alphabetic() && bytes[1] == b':' &&
+            (bytes[2] == b'\\' || bytes[2] == b'/') {
+            return false;
+        }
+    }
+    true
+}
+
+/// Build a PathBuf inside `base` from `user_name`.
+fn resolved_path_in_base(base: &Path, user_name: &str) -> Result<PathBuf, Error> {
+    if !is_safe_path_component(user_name) {
+        return Err(Error::UnsafePath(user_name.to_string()));
+    }
+    Ok(base.join(user_name))
+}
+
+/// Copy `src` to `dst` atomically: write into a sibling temp file, `fsync` it,
+/// then `rename` it over the destination so an interrupted copy can never leave
+/// a half-written file where a good one used to be. The temp file lives in the
+/// destination's own directory, so the rename is always same-filesystem; a
+/// failed operation always cleans up the temp file.
+///
+/// No cross-device (`EXDEV`) copy-then-remove fallback is needed: because the
+/// temp file is a sibling of `dst`, the rename can never straddle filesystems,
+/// so that branch would be provably unreachable.
+fn copy_file(src: &Path, dst: &Path) -> io::Result<u64> {
+    let dir = dst.parent().unwrap_or_else(|| Path::new("."));
+    let name = dst.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let tmp = dir.join(format!(".{}.tmp.{}", name, std::process::id()));
+
+    let result = (|| {
+        let bytes = fs::copy(src, &tmp)?;
+        // Flush the temp file's contents to disk before the atomic swap.
+        fs::File::open(&tmp)?.sync_all()?;
+        fs::rename(&tmp, dst)?;
+        Ok(bytes)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Backup a file
+fn backup_file(base: &Path, filename: &str) -> Result<PathBuf, Error> {
+    let src = resolved_path_in_base(base, filename)?;
+    if !src.exists() {
+        return Err(Error::NotFound(src));
+    }
+    let bak_name = format!("{}.bak", filename);
+    let dst = resolved_path_in_base(base, &bak_name)?;
+    copy_file(&src, &dst)?;
+    append_log(&format!("backup {} -> {}", filename, bak_name));
+    Ok(dst)
+}
+
+/// Restore a file from its `.bak`. By design this refuses to overwrite an
+/// existing target unless `force` is set, so the common case of restoring over
+/// the current file requires `-f`/`--force` (the interactive and shell paths
+/// pass `force=false` and will report `destination already exists`).
+fn restore_file(base: &Path, filename: &str, force: bool) -> Result<PathBuf, Error> {
+    let bak_name = format!("{}.bak", filename);
+    let bak = resolved_path_in_base(base, &bak_name)?;
+    if !bak.exists() {
+        return Err(Error::NotFound(bak));
+    }
+    let dst = resolved_path_in_base(base, filename)?;
+    if dst.exists() && !force {
+        return Err(Error::DestinationExists(dst));
+    }
+    copy_file(&bak, &dst)?;
+    append_log(&format!("restore {} <- {}", filename, bak_name));
+    Ok(dst)
+}
+
+/// The trash directory holding recoverable files, under `base`.
+fn trash_files_dir(base: &Path) -> PathBuf {
+    base.join(".trash").join("files")
+}
+
+/// The trash directory holding `.trashinfo` metadata records, under `base`.
+fn trash_info_dir(base: &Path) -> PathBuf {
+    base.join(".trash").join("info")
+}
+
+/// Pick a non-colliding name inside `.trash/files`. Returns `filename` if it is
+/// free, otherwise appends a numeric suffix (`foo.txt.2`, `foo.txt.3`, ...).
+fn unique_trash_name(files_dir: &Path, info_dir: &Path, filename: &str) -> String {
+    if !files_dir.join(filename).exists() && !info_dir.join(format!("{}.trashinfo", filename)).exists()
+    {
+        return filename.to_string();
+    }
+    let mut n = 2;
+    loop {
+        let candidate = format!("{}.{}", filename, n);
+        if !files_dir.join(&candidate).exists()
+            && !info_dir.join(format!("{}.trashinfo", candidate)).exists()
+        {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// Move a file into the trash instead of unlinking it. Writes a sibling
+/// `.trashinfo` record holding the original relative path and an ISO-8601
+/// deletion timestamp, following the freedesktop trash spec.
+fn delete_file(base: &Path, filename: &str, force: bool) -> Result<(), Error> {
+    let p = resolved_path_in_base(base, filename)?;
+    if !p.exists() {
+        if force {
+            return Ok(());
+        }
+        return Err(Error::NotFound(p));
+    }
+
+    let files_dir = trash_files_dir(base);
+    let info_dir = trash_info_dir(base);
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    let trashed = unique_trash_name(&files_dir, &info_dir, filename);
+    let dst = files_dir.join(&trashed);
+    fs::rename(&p, &dst)?;
+
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        filename,
+        iso8601_utc(ts)
+    );
+    fs::write(info_dir.join(format!("{}.trashinfo", trashed)), info)?;
+
+    append_log(&format!("delete {} -> .trash/files/{}", filename, trashed));
+    Ok(())
+}
+
+/// Parse the `Path=` field out of a `.trashinfo` record.
+fn trashinfo_original_path(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .find_map(|l| l.strip_prefix("Path=").map(|p| p.to_string()))
+}
+
+/// Restore a trashed file to its recorded original path, refusing if that path
+/// already exists. Removes the `.trashinfo` record once the move succeeds.
+fn untrash_file(base: &Path, trashed: &str) -> Result<PathBuf, Error> {
+    if !is_safe_path_component(trashed) {
+        return Err(Error::UnsafePath(trashed.to_string()));
+    }
+    let files_dir = trash_files_dir(base);
+    let info_dir = trash_info_dir(base);
+
+    let src = files_dir.join(trashed);
+    let info_path = info_dir.join(format!("{}.trashinfo", trashed));
+    if !src.exists() || !info_path.exists() {
+        return Err(Error::NotFound(src));
+    }
+
+    let original = trashinfo_original_path(&fs::read_to_string(&info_path)?)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed trashinfo"))?;
+    let dst = resolved_path_in_base(base, &original)?;
+    if dst.exists() {
+        return Err(Error::DestinationExists(dst));
+    }
+
+    fs::rename(&src, &dst)?;
+    fs::remove_file(&info_path)?;
+    append_log(&format!("untrash .trash/files/{} -> {}", trashed, original));
+    Ok(dst)
+}
+
+/// Permanently delete every file in the trash and its metadata records.
+fn purge_all(base: &Path) -> Result<(), Error> {
+    for dir in [trash_files_dir(base), trash_info_dir(base)] {
+        if !dir.exists() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    append_log("empty trash");
+    Ok(())
+}
+
+/// List the candidate filenames for a batch rename: regular files directly
+/// under `base`, excluding dotfiles (e.g. `.trash`) and the audit log.
+fn rename_candidates(base: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(base)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || name == "logfile.txt" {
+            continue;
+        }
+        names.push(name);
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Launch `$EDITOR` (falling back to `notepad` on Windows and `vi` elsewhere)
+/// on `path` and wait for it to exit.
+fn launch_editor(path: &Path) -> io::Result<()> {
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| {
+        if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() }
+    });
+    let status = std::process::Command::new(&editor).arg(path).status()?;
+    if !status.success() {
+        return Err(io::Error::other("editor exited with an error"));
+    }
+    Ok(())
+}
+
+/// Return the first duplicated value in `names`, if any.
+fn first_duplicate(names: &[String]) -> Option<String> {
+    let mut seen = std::collections::HashSet::new();
+    names.iter().find(|n| !seen.insert(n.as_str())).cloned()
+}
+
+/// Rename/move many files at once through the user's `$EDITOR`, following the
+/// mmv pattern: the `old` names are written one-per-line into a temp file, the
+/// editor is launched, and each edited line at index `i` renames `old[i]` to the
+/// new text. Line count must be preserved; input and output names must each be
+/// unique; every new name is validated through [`is_safe_path_component`].
+fn batch_rename(base: &Path, old: &[String]) -> Result<usize, Error> {
+    if old.is_empty() {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no files to rename",
+        )));
+    }
+    if let Some(dup) = first_duplicate(old) {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("duplicate input name: {}", dup),
+        )));
+    }
+
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let tmp = std::env::temp_dir().join(format!("safebackup-rename-{}.txt", ts));
+    fs::write(&tmp, format!("{}\n", old.join("\n")))?;
+
+    let edit_result = (|| {
+        launch_editor(&tmp)?;
+        let contents = fs::read_to_string(&tmp)?;
+        let new: Vec<String> = contents.lines().map(|l| l.trim().to_string()).collect();
+
+        if new.len() != old.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "files added or removed during editing",
+            ));
+        }
+        if let Some(dup) = first_duplicate(&new) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("duplicate output name: {}", dup),
+            ));
+        }
+        Ok(new)
+    })();
+    let new = match edit_result {
+        Ok(new) => new,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            return Err(Error::Io(e));
+        }
+    };
+    let _ = fs::remove_file(&tmp);
+
+    // Collect the entries that actually changed, validating new names.
+    let mut changes: Vec<(String, String)> = Vec::new();
+    for (o, n) in old.iter().zip(new.iter()) {
+        if o == n {
+            continue;
+        }
+        if n.is_empty() || !is_safe_path_component(n) {
+            return Err(Error::UnsafePath(n.clone()));
+        }
+        changes.push((o.clone(), n.clone()));
+    }
+    if changes.is_empty() {
+        return Ok(0);
+    }
+
+    // A new name may only land on a path that this batch itself vacates; any
+    // other existing file would be silently clobbered, so refuse up front.
+    let moved: std::collections::HashSet<&str> =
+        changes.iter().map(|(o, _)| o.as_str()).collect();
+    for (_, n) in &changes {
+        let dst = resolved_path_in_base(base, n)?;
+        if dst.exists() && !moved.contains(n.as_str()) {
+            return Err(Error::DestinationExists(dst));
+        }
+    }
+
+    // Move every source aside to a unique temp name first, so cycles and swaps
+    // (a->b, b->a) never overwrite a file that is still needed.
+    let mut staged: Vec<(PathBuf, String)> = Vec::new();
+    for (i, (o, n)) in changes.iter().enumerate() {
+        let src = resolved_path_in_base(base, o)?;
+        let stage = base.join(format!(".rename.{}.{}.tmp", ts, i));
+        fs::rename(&src, &stage)?;
+        staged.push((stage, n.clone()));
+    }
+    for (stage, n) in &staged {
+        let dst = resolved_path_in_base(base, n)?;
+        fs::rename(stage, &dst)?;
+    }
+
+    append_log(&format!("rename {} file(s)", changes.len()));
+    Ok(changes.len())
+}
+
+/// Prompt user input
+fn prompt(msg: &str) -> io::Result<String> {
+    print!("{}", msg);
+    io::stdout().flush()?;
+    let mut s = String::new();
+    io::stdin().read_line(&mut s)?;
+    Ok(s.trim().to_string())
+}
+
+/// Pause for Enter key (keeps console open)
+fn wait_for_enter() {
+    print!("Press Enter to exit...");
+    let _ = io::stdout().flush();
+    let mut dummy = String::new();
+    let _ = io::stdin().read_line(&mut dummy);
+}
+
+/// The fixed command list offered by the shell and its tab-completion.
+const SHELL_COMMANDS: [&str; 5] = ["backup", "restore", "delete", "help", "exit"];
+
+/// Compute tab-completion candidates for the shell. The first token completes
+/// against [`SHELL_COMMANDS`]; any later token completes against real directory
+/// entries under `base` whose names start with `token`. Tokens that fail the
+/// [`is_safe_path_component`] guard offer no completions, so traversal attempts
+/// are rejected before they are ever suggested.
+fn shell_completions(base: &Path, first_token: bool, token: &str) -> Vec<String> {
+    if first_token {
+        return SHELL_COMMANDS
+            .iter()
+            .filter(|c| c.starts_with(token))
+            .map(|c| c.to_string())
+            .collect();
+    }
+    if !token.is_empty() && !is_safe_path_component(token) {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
+    if let Ok(entries) = fs::read_dir(base) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(token) {
+                out.push(name);
+            }
+        }
+    }
+    out.sort();
+    out
+}
+
+/// The longest common prefix shared by every string in `items`, counted in
+/// whole `char`s so the result never lands mid-UTF-8-character.
+fn common_prefix(items: &[String]) -> String {
+    let first = match items.first() {
+        Some(s) => s.as_str(),
+        None => return String::new(),
+    };
+    let mut len = first.chars().count();
+    for s in &items[1..] {
+        len = first
+            .chars()
+            .zip(s.chars())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+    first.chars().take(len).collect()
+}
+
+/// Read a single line with Tab-completion in raw terminal mode (via `stty`),
+/// returning `None` on EOF. Completes the current token against commands or
+/// `base` directory entries, like the MOROS shell.
+#[cfg(unix)]
+fn read_shell_line(base: &Path) -> io::Result<Option<String>> {
+    let saved = stty(&["-g"])?;
+    stty(&["-icanon", "-echo", "min", "1", "time", "0"])?;
+    let result = read_shell_line_raw(base);
+    let _ = stty(&[saved.trim()]);
+    println!();
+    result
+}
+
+/// Fallback line reader for non-unix targets, without raw-mode completion.
+#[cfg(not(unix))]
+fn read_shell_line(_base: &Path) -> io::Result<Option<String>> {
+    print!("safebackup> ");
+    io::stdout().flush()?;
+    let mut s = String::new();
+    if io::stdin().read_line(&mut s)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(s.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Invoke `stty` with `args`, returning its captured stdout.
+#[cfg(unix)]
+fn stty(args: &[&str]) -> io::Result<String> {
+    let out = std::process::Command::new("stty").args(args).output()?;
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// The raw-mode read loop. The terminal is assumed to already be in raw mode.
+#[cfg(unix)]
+fn read_shell_line_raw(base: &Path) -> io::Result<Option<String>> {
+    let prompt = "safebackup> ";
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut buf = String::new();
+    let mut stdin = io::stdin();
+    let mut byte = [0u8; 1];
+    while stdin.read(&mut byte)? != 0 {
+        match byte[0] {
+            b'\n' | b'\r' => return Ok(Some(buf)),
+            0x03 => return Ok(Some(String::new())), // Ctrl-C: cancel the line
+            0x04 if buf.is_empty() => return Ok(None), // Ctrl-D on an empty line: EOF
+            0x7f | 0x08 if buf.pop().is_some() => {
+                print!("\x08 \x08");
+                io::stdout().flush()?;
+            }
+            b'\t' => {
+                let trailing_space = buf.ends_with(' ');
+                let tokens: Vec<&str> = buf.split_whitespace().collect();
+                let first_token = tokens.len() <= 1 && !trailing_space;
+                let current = if trailing_space {
+                    ""
+                } else {
+                    tokens.last().copied().unwrap_or("")
+                };
+
+                let matches = shell_completions(base, first_token, current);
+                let completion = common_prefix(&matches);
+                if completion.len() > current.len() {
+                    let suffix = &completion[current.len()..];
+                    buf.push_str(suffix);
+                    print!("{}", suffix);
+                    io::stdout().flush()?;
+                } else if matches.len() > 1 {
+                    println!();
+                    println!("{}", matches.join("  "));
+                    print!("{}{}", prompt, buf);
+                    io::stdout().flush()?;
+                }
+            }
+            c if (0x20..0x7f).contains(&c) => {
+                buf.push(c as char);
+                print!("{}", c as char);
+                io::stdout().flush()?;
+            }
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Drop into a persistent prompt, dispatching commands repeatedly without
+/// restarting the process. Supports Tab-completion of commands and filenames.
+fn run_shell(base: &Path) -> io::Result<()> {
+    println!("safebackup shell — type 'help' for commands, 'exit' to quit.");
+    while let Some(line) = read_shell_line(base)? {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let command = parts.next().unwrap();
+        let files: Vec<&str> = parts.collect();
+
+        match command {
+            "exit" | "quit" => break,
+            "help" => {
+                println!("commands: backup <file>, restore <file> (use the CLI with -f to overwrite), delete <file>, help, exit");
+            }
+            "backup" | "restore" | "delete" => {
+                if files.is_empty() {
+                    eprintln!("usage: {} <file>...", command);
+                    continue;
+                }
+                for f in &files {
+                    let result = match command {
+                        "backup" => backup_file(base, f).map(|_| ()),
+                        "restore" => restore_file(base, f, false).map(|_| ()),
+                        _ => delete_file(base, f, false),
+                    };
+                    match result {
+                        Ok(()) => println!("{}: ok", f),
+                        Err(e) => eprintln!("{}: {}", f, e),
+                    }
+                }
+            }
+            other => eprintln!("unknown command: {} (try 'help')", other),
+        }
+    }
+    Ok(())
+}
+
+/// Behavioural flags parsed from the command line.
+#[derive(Debug, Default, Clone, Copy)]
+struct Options {
+    /// `-f`/`--force`: restore overwrites an existing target; delete skips the
+    /// existence check.
+    force: bool,
+    /// `-q`/`--quiet`: suppress success messages.
+    quiet: bool,
+    /// `-0`/`--null`: read NUL-separated filenames from stdin.
+    null: bool,
+}
+
+/// A parsed non-interactive invocation: a command, its filename operands, and
+/// the flag set.
+struct Invocation {
+    command: String,
+    files: Vec<String>,
+    options: Options,
+}
+
+/// Parse `args` (everything after the program name) into an [`Invocation`].
+/// Flags may appear anywhere; `--` forces the rest to be treated as operands.
+fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let mut options = Options::default();
+    let mut positional = Vec::new();
+    let mut only_positional = false;
+
+    for arg in args {
+        if only_positional || !arg.starts_with('-') || arg == "-" {
+            positional.push(arg.clone());
+            continue;
+        }
+        match arg.as_str() {
+            "--" => only_positional = true,
+            "-f" | "--force" => options.force = true,
+            "-q" | "--quiet" => options.quiet = true,
+            "-0" | "--null" => options.null = true,
+            other => return Err(format!("unknown flag: {}", other)),
+        }
+    }
+
+    if positional.is_empty() {
+        return Err("no command given".to_string());
+    }
+    let command = positional.remove(0);
+    Ok(Invocation { command, files: positional, options })
+}
+
+/// Read NUL-separated filenames from stdin, for `-0`/`--null` batch operations.
+fn read_null_stdin() -> io::Result<Vec<String>> {
+    let mut buf = String::new();
+    io::stdin().read_to_string(&mut buf)?;
+    Ok(buf
+        .split('\0')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect())
+}
+
+/// Execute a parsed non-interactive invocation, returning the process exit code.
+fn run_cli(base: &Path, inv: Invocation) -> i32 {
+    let Invocation { command, mut files, options } = inv;
+    if options.null {
+        match read_null_stdin() {
+            Ok(extra) => files.extend(extra),
+            Err(e) => {
+                eprintln!("Failed to read stdin: {}", e);
+                return 5;
+            }
+        }
+    }
+
+    // Run `op` over each filename, stopping at the first failure and returning
+    // its exit code; later filenames are not processed.
+    let each = |files: &[String], op: &dyn Fn(&str) -> Result<(), Error>| -> i32 {
+        if files.is_empty() {
+            eprintln!("No filenames given.");
+            return 1;
+        }
+        for f in files {
+            if let Err(e) = op(f) {
+                eprintln!("{}: {}", f, e);
+                return e.exit_code();
+            }
+        }
+        0
+    };
+
+    match command.as_str() {
+        "backup" => each(&files, &|f| {
+            let dst = backup_file(base, f)?;
+            if !options.quiet {
+                println!("Backup created: {}", dst.file_name().unwrap().to_string_lossy());
+            }
+            Ok(())
+        }),
+        "restore" => each(&files, &|f| {
+            restore_file(base, f, options.force)?;
+            if !options.quiet {
+                println!("File restored: {}", f);
+            }
+            Ok(())
+        }),
+        "delete" => each(&files, &|f| {
+            delete_file(base, f, options.force)?;
+            if !options.quiet {
+                println!("File moved to trash: {}", f);
+            }
+            Ok(())
+        }),
+        "untrash" => each(&files, &|f| {
+            let dst = untrash_file(base, f)?;
+            if !options.quiet {
+                println!("File restored from trash: {}", dst.display());
+            }
+            Ok(())
+        }),
+        "empty" => match purge_all(base) {
+            Ok(_) => {
+                if !options.quiet {
+                    println!("Trash emptied.");
+                }
+                0
+            }
+            Err(e) => {
+                eprintln!("Failed to empty trash: {}", e);
+                e.exit_code()
+            }
+        },
+        "rename" => {
+            let targets = if files.is_empty() {
+                match rename_candidates(base) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        eprintln!("Failed to list files: {}", e);
+                        return 1;
+                    }
+                }
+            } else {
+                files
+            };
+            match batch_rename(base, &targets) {
+                Ok(n) => {
+                    if !options.quiet {
+                        println!("Renamed {} file(s).", n);
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Failed to rename: {}", e);
+                    e.exit_code()
+                }
+            }
+        }
+        "shell" => match run_shell(base) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("shell error: {}", e);
+                5
+            }
+        },
+        other => {
+            eprintln!("Invalid command: {}", other);
+            1
+        }
+    }
+}
+
+/// The original blocking, one-command-then-exit interactive flow, used as a
+/// fallback when no command-line arguments are given.
+fn run_interactive(base: &Path) -> io::Result<()> {
+    println!("=== SafeBackup (Rust) ===");
+
+    let filename = prompt("Please enter your file name: ")?;
+    if !is_safe_path_component(&filename) {
+        eprintln!("Error: unsafe filename detected.");
+        append_log(&format!("rejected unsafe filename input: {}", filename));
+        wait_for_enter();
+        std::process::exit(1);
+    }
+
+    let command = prompt("Please enter your command (backup, restore, delete, untrash, empty, rename): ")?;
+    match command.as_str() {
+        "backup" => match backup_file(base, &filename) {
+            Ok(dst) => println!("Your backup created: {}", dst.file_name().unwrap().to_string_lossy()),
+            Err(e) => { eprintln!("Failed to create backup: {}", e); wait_for_enter(); std::process::exit(e.exit_code()); }
+        },
+        "restore" => match restore_file(base, &filename, false) {
+            Ok(_) => println!("File restored: {}", filename),
+            Err(e) => { eprintln!("Failed to restore: {}", e); wait_for_enter(); std::process::exit(e.exit_code()); }
+        },
+        "delete" => match delete_file(base, &filename, false) {
+            Ok(_) => println!("File moved to trash: {}", filename),
+            Err(e) => { eprintln!("Failed to delete: {}", e); wait_for_enter(); std::process::exit(e.exit_code()); }
+        },
+        "untrash" => match untrash_file(base, &filename) {
+            Ok(dst) => println!("File restored from trash: {}", dst.display()),
+            Err(e) => { eprintln!("Failed to untrash: {}", e); wait_for_enter(); std::process::exit(e.exit_code()); }
+        },
+        "empty" => match purge_all(base) {
+            Ok(_) => println!("Trash emptied."),
+            Err(e) => { eprintln!("Failed to empty trash: {}", e); wait_for_enter(); std::process::exit(e.exit_code()); }
+        },
+        "rename" => {
+            let candidates = match rename_candidates(base) {
+                Ok(c) => c,
+                Err(e) => { eprintln!("Failed to list files: {}", e); wait_for_enter(); std::process::exit(1); }
+            };
+            match batch_rename(base, &candidates) {
+                Ok(n) => println!("Renamed {} file(s).", n),
+                Err(e) => { eprintln!("Failed to rename: {}", e); wait_for_enter(); std::process::exit(e.exit_code()); }
+            }
+        },
+        _ => { eprintln!("Invalid command."); wait_for_enter(); std::process::exit(1); }
+    }
+
+    wait_for_enter(); // keep console open after successful operation
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let base = std::env::current_dir()?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        return run_interactive(&base);
+    }
+
+    match parse_args(&args) {
+        Ok(inv) => std::process::exit(run_cli(&base, inv)),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("Usage: safebackup <backup|restore|delete|untrash|empty|rename|shell> [files...] [-f] [-q] [-0]");
+            eprintln!("Note: 'restore' refuses to overwrite an existing file unless -f/--force is given.");
+            std::process::exit(1);
+        }
+    }
+}